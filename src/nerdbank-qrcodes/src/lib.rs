@@ -1,8 +1,14 @@
+mod pin_qr;
+
 use encoding::{all::UTF_16LE, DecoderTrap, Encoding};
 use image::{self, DynamicImage};
 use rxing::{
-    common::HybridBinarizer, BarcodeFormat, BinaryBitmap, BufferedImageLuminanceSource,
-    DecodeHintValue, DecodeHints, Exceptions, MultiFormatReader, RXingResult, Reader,
+    common::{GlobalHistogramBinarizer, HybridBinarizer},
+    multi::{GenericMultipleBarcodeReader, MultipleBarcodeReader},
+    qrcode::QRCodeMultiReader,
+    BarcodeFormat, BinaryBitmap, BufferedImageLuminanceSource, DecodeHintValue, DecodeHints,
+    Exceptions, MultiFormatReader, PlanarYUVLuminanceSource, RXingResult, RXingResultMetadataType,
+    RXingResultMetadataValue, Reader,
 };
 use std::collections::HashSet;
 
@@ -10,6 +16,54 @@ const QR_DECODE_NO_QR_CODE: i32 = 0;
 const INVALID_UTF16_STRING: i32 = -1;
 const IMAGE_ERROR: i32 = -2;
 const QR_DECODE_ERROR: i32 = -3;
+const PIN_DECRYPT_ERROR: i32 = -4;
+
+/// Stable FFI codes for barcode formats, independent of rxing's own
+/// `BarcodeFormat` discriminants, so the FFI surface has one consistent format
+/// numbering everywhere a format crosses it: both the formats
+/// `decode_barcode_from_image` callers may request and the format
+/// `QrCodeDetails.format` reports back.
+const FORMAT_QR_CODE: i32 = 0;
+const FORMAT_AZTEC: i32 = 1;
+const FORMAT_DATA_MATRIX: i32 = 2;
+const FORMAT_PDF_417: i32 = 3;
+const FORMAT_CODE_128: i32 = 4;
+const FORMAT_CODE_39: i32 = 5;
+const FORMAT_EAN_13: i32 = 6;
+const FORMAT_EAN_8: i32 = 7;
+const FORMAT_UPC_A: i32 = 8;
+const FORMAT_UPC_E: i32 = 9;
+/// Reported when a decoded symbol's format has no stable FFI code yet.
+const FORMAT_UNKNOWN: i32 = -1;
+
+/// Images outside these pixel bounds are rejected before decoding is attempted,
+/// since they can't plausibly contain a scannable barcode. The `*_WIDTH` bounds
+/// apply to the image's longer side and the `*_HEIGHT` bounds to its shorter
+/// side, whichever way round the image is.
+const MIN_IMAGE_WIDTH: u32 = 26;
+const MIN_IMAGE_HEIGHT: u32 = 10;
+const MAX_IMAGE_WIDTH: u32 = 2200;
+const MAX_IMAGE_HEIGHT: u32 = 2200;
+
+/// Scalar metadata about a decoded barcode, written by
+/// [`decode_qr_code_details_from_image`] alongside the raw byte payload and
+/// finder-pattern points, which are returned through their own caller-owned
+/// buffers since their length isn't known up front.
+#[repr(C)]
+pub struct QrCodeDetails {
+    /// One of the stable `FORMAT_*` codes (see above), or `FORMAT_UNKNOWN`.
+    pub format: i32,
+    /// ASCII code of the error-correction level letter (`L`, `M`, `Q`, `H`), or 0 if unknown.
+    pub ecc_level: u8,
+    /// Symbol version/size indicator, or -1 if not reported by the decoder.
+    pub symbol_version: i32,
+    /// Number of bytes written into the caller's `raw_bytes` buffer (or that would
+    /// have been written, had the buffer been large enough).
+    pub raw_bytes_length: usize,
+    /// Number of points written into the caller's `points` buffer (or that would
+    /// have been written, had the buffer been large enough), two floats each.
+    pub point_count: usize,
+}
 
 #[no_mangle]
 pub extern "C" fn decode_qr_code_from_file(
@@ -38,12 +92,48 @@ pub extern "C" fn decode_qr_code_from_image(
     image_buffer_len: usize,
     decoded: *mut u16,
     decoded_length: usize,
+) -> i32 {
+    decode_qr_code_from_image_impl(image_buffer, image_buffer_len, false, decoded, decoded_length)
+}
+
+/// Same as [`decode_qr_code_from_image`], with the slower binarizer/rotation
+/// fallback chain available via `exhaustive`. Kept as a separate export rather
+/// than adding a parameter to `decode_qr_code_from_image`, since that function
+/// already shipped and inserting a parameter into an existing `extern "C"`
+/// signature would silently break already-compiled callers.
+#[no_mangle]
+pub extern "C" fn decode_qr_code_from_image_exhaustive(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    exhaustive: bool,
+    decoded: *mut u16,
+    decoded_length: usize,
+) -> i32 {
+    decode_qr_code_from_image_impl(
+        image_buffer,
+        image_buffer_len,
+        exhaustive,
+        decoded,
+        decoded_length,
+    )
+}
+
+fn decode_qr_code_from_image_impl(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    exhaustive: bool,
+    decoded: *mut u16,
+    decoded_length: usize,
 ) -> i32 {
     let image_buffer = unsafe { std::slice::from_raw_parts(image_buffer, image_buffer_len) };
 
     if let Ok(image) = image::load_from_memory(image_buffer) {
         process_result(
-            detect_in_file_with_hints(image, Some(BarcodeFormat::QR_CODE)),
+            detect_in_file_with_hints(
+                image,
+                Some(HashSet::from([BarcodeFormat::QR_CODE])),
+                exhaustive,
+            ),
             decoded,
             decoded_length,
         )
@@ -52,6 +142,636 @@ pub extern "C" fn decode_qr_code_from_image(
     }
 }
 
+#[no_mangle]
+pub extern "C" fn decode_barcode_from_image(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    formats: *const i32,
+    formats_len: usize,
+    decoded: *mut u16,
+    decoded_length: usize,
+) -> i32 {
+    decode_barcode_from_image_impl(
+        image_buffer,
+        image_buffer_len,
+        formats,
+        formats_len,
+        false,
+        decoded,
+        decoded_length,
+    )
+}
+
+/// Same as [`decode_barcode_from_image`], with the slower binarizer/rotation
+/// fallback chain available via `exhaustive`. See
+/// [`decode_qr_code_from_image_exhaustive`] for why this is a separate export.
+#[no_mangle]
+pub extern "C" fn decode_barcode_from_image_exhaustive(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    formats: *const i32,
+    formats_len: usize,
+    exhaustive: bool,
+    decoded: *mut u16,
+    decoded_length: usize,
+) -> i32 {
+    decode_barcode_from_image_impl(
+        image_buffer,
+        image_buffer_len,
+        formats,
+        formats_len,
+        exhaustive,
+        decoded,
+        decoded_length,
+    )
+}
+
+fn decode_barcode_from_image_impl(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    formats: *const i32,
+    formats_len: usize,
+    exhaustive: bool,
+    decoded: *mut u16,
+    decoded_length: usize,
+) -> i32 {
+    let image_buffer = unsafe { std::slice::from_raw_parts(image_buffer, image_buffer_len) };
+
+    let image = match image::load_from_memory(image_buffer) {
+        Ok(image) => image,
+        Err(_) => return IMAGE_ERROR,
+    };
+
+    let formats = if formats_len == 0 {
+        None
+    } else {
+        let formats_slice = unsafe { std::slice::from_raw_parts(formats, formats_len) };
+        Some(
+            formats_slice
+                .iter()
+                .filter_map(|code| barcode_format_from_code(*code))
+                .collect::<HashSet<_>>(),
+        )
+    };
+
+    process_result(
+        detect_in_file_with_hints(image, formats, exhaustive),
+        decoded,
+        decoded_length,
+    )
+}
+
+#[no_mangle]
+pub extern "C" fn decode_qr_code_from_luminance(
+    luminance_buffer: *const u8,
+    width: u32,
+    height: u32,
+    stride: u32,
+    exhaustive: bool,
+    decoded: *mut u16,
+    decoded_length: usize,
+) -> i32 {
+    // `stride < width` would make each row's `width`-wide slice run past the row
+    // into (or beyond) the next one; reject it before touching the buffer.
+    if !is_within_sane_pixel_bounds(width, height) || stride < width {
+        return QR_DECODE_NO_QR_CODE;
+    }
+
+    let luminance_buffer_len = stride as usize * height as usize;
+    let luminance_buffer =
+        unsafe { std::slice::from_raw_parts(luminance_buffer, luminance_buffer_len) };
+
+    process_result(
+        decode_luminance_with_hints(
+            luminance_buffer,
+            width as usize,
+            height as usize,
+            stride as usize,
+            Some(HashSet::from([BarcodeFormat::QR_CODE])),
+            exhaustive,
+        ),
+        decoded,
+        decoded_length,
+    )
+}
+
+/// Decodes directly from a raw 8-bit luminance plane (e.g. a V4L2/MediaCapture
+/// camera frame's Y plane), skipping `image::load_from_memory` entirely so a
+/// live scanning loop isn't stuck paying a full image-format decode per frame.
+fn decode_luminance_with_hints(
+    luminance: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    formats: Option<HashSet<BarcodeFormat>>,
+    exhaustive: bool,
+) -> Result<RXingResult, Exceptions> {
+    let mut multi_format_reader = MultiFormatReader::default();
+
+    let mut hints = DecodeHints::default().with(DecodeHintValue::TryHarder(true));
+    if let Some(formats) = formats {
+        if !formats.is_empty() {
+            hints = hints.with(DecodeHintValue::PossibleFormats(formats));
+        }
+    }
+
+    let result =
+        decode_luminance_with_binarizer(&mut multi_format_reader, luminance, width, height, stride, &hints, false);
+    if !exhaustive {
+        return result;
+    }
+
+    match result {
+        Err(Exceptions::NotFoundException(_)) => decode_luminance_exhaustively(
+            &mut multi_format_reader,
+            luminance,
+            width,
+            height,
+            stride,
+            &hints,
+        ),
+        other => other,
+    }
+}
+
+/// Mirrors `decode_exhaustively`'s HybridBinarizer → GlobalHistogramBinarizer →
+/// 90°/180° rotation fallback chain for a raw luminance plane, so this entry
+/// point's `exhaustive` flag gives the same fallback coverage as the image-file
+/// entry points instead of silently dropping the rotation retries.
+fn decode_luminance_exhaustively(
+    reader: &mut MultiFormatReader,
+    luminance: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    hints: &DecodeHints,
+) -> Result<RXingResult, Exceptions> {
+    // The unrotated plane already failed with the HybridBinarizer above, so only
+    // its GlobalHistogramBinarizer retry is left; each rotation gets both.
+    match decode_luminance_with_binarizer(reader, luminance, width, height, stride, hints, true) {
+        Ok(r) => return Ok(r),
+        Err(Exceptions::NotFoundException(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    let cropped = extract_cropped_luminance(luminance, width, height, stride);
+    let rotated_90 = rotate_luminance_90(&cropped, width, height);
+    let rotated_180 = rotate_luminance_180(&cropped);
+
+    for (rotated, rotated_width, rotated_height) in
+        [(rotated_90, height, width), (rotated_180, width, height)]
+    {
+        for use_global_histogram in [false, true] {
+            match decode_luminance_with_binarizer(
+                reader,
+                &rotated,
+                rotated_width,
+                rotated_height,
+                rotated_width,
+                hints,
+                use_global_histogram,
+            ) {
+                Ok(r) => return Ok(r),
+                Err(Exceptions::NotFoundException(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Err(Exceptions::NotFoundException(None))
+}
+
+fn decode_luminance_with_binarizer(
+    reader: &mut MultiFormatReader,
+    luminance: &[u8],
+    width: usize,
+    height: usize,
+    stride: usize,
+    hints: &DecodeHints,
+    use_global_histogram: bool,
+) -> Result<RXingResult, Exceptions> {
+    let source =
+        PlanarYUVLuminanceSource::new(luminance.to_vec(), stride, height, 0, 0, width, height, false);
+    if use_global_histogram {
+        reader.decode_with_hints(
+            &mut BinaryBitmap::new(GlobalHistogramBinarizer::new(source)),
+            hints,
+        )
+    } else {
+        reader.decode_with_hints(
+            &mut BinaryBitmap::new(HybridBinarizer::new(source)),
+            hints,
+        )
+    }
+}
+
+/// Copies out the tightly-packed `width`×`height` plane from a possibly padded
+/// row `stride`, so the rotation helpers below don't need to carry stride math.
+fn extract_cropped_luminance(buf: &[u8], width: usize, height: usize, stride: usize) -> Vec<u8> {
+    if stride == width {
+        return buf[..width * height].to_vec();
+    }
+
+    let mut cropped = Vec::with_capacity(width * height);
+    for row in 0..height {
+        let start = row * stride;
+        cropped.extend_from_slice(&buf[start..start + width]);
+    }
+    cropped
+}
+
+/// Rotates a tightly-packed `width`×`height` luminance plane 90° clockwise into
+/// a `height`×`width` plane.
+fn rotate_luminance_90(buf: &[u8], width: usize, height: usize) -> Vec<u8> {
+    let mut rotated = vec![0u8; width * height];
+    for y in 0..height {
+        for x in 0..width {
+            let new_x = height - 1 - y;
+            let new_y = x;
+            rotated[new_y * height + new_x] = buf[y * width + x];
+        }
+    }
+    rotated
+}
+
+/// Rotates a tightly-packed luminance plane 180°; dimensions are unchanged.
+fn rotate_luminance_180(buf: &[u8]) -> Vec<u8> {
+    let mut rotated = buf.to_vec();
+    rotated.reverse();
+    rotated
+}
+
+#[no_mangle]
+pub extern "C" fn encode_qr_code_with_pin(
+    secret: *const u8,
+    secret_len: usize,
+    pin: u32,
+    encoded: *mut u16,
+    encoded_length: usize,
+) -> i32 {
+    let secret = unsafe { std::slice::from_raw_parts(secret, secret_len) };
+    let payload = pin_qr::encrypt_secret(secret, pin);
+
+    let encoded_slice = unsafe { std::slice::from_raw_parts_mut(encoded, encoded_length) };
+    let mut content_len = 0;
+    for (i, c) in payload.encode_utf16().enumerate() {
+        if i < encoded_length {
+            encoded_slice[i] = c;
+        }
+        content_len += 1;
+    }
+    content_len
+}
+
+#[no_mangle]
+pub extern "C" fn decode_qr_code_with_pin_from_image(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    exhaustive: bool,
+    pin: u32,
+    decrypted: *mut u8,
+    decrypted_capacity: usize,
+) -> i32 {
+    let image_buffer = unsafe { std::slice::from_raw_parts(image_buffer, image_buffer_len) };
+
+    let image = match image::load_from_memory(image_buffer) {
+        Ok(image) => image,
+        Err(_) => return IMAGE_ERROR,
+    };
+
+    let result = detect_in_file_with_hints(
+        image,
+        Some(HashSet::from([BarcodeFormat::QR_CODE])),
+        exhaustive,
+    );
+
+    let payload = match result {
+        Ok(r) => r.getText().to_owned(),
+        Err(Exceptions::NotFoundException(_)) => return QR_DECODE_NO_QR_CODE,
+        Err(_) => return QR_DECODE_ERROR,
+    };
+
+    match pin_qr::decrypt_secret(&payload, pin) {
+        Ok(secret) => {
+            let decrypted_slice =
+                unsafe { std::slice::from_raw_parts_mut(decrypted, decrypted_capacity) };
+            for (i, b) in secret.iter().enumerate() {
+                if i < decrypted_capacity {
+                    decrypted_slice[i] = *b;
+                }
+            }
+            secret.len() as i32
+        }
+        Err(_) => PIN_DECRYPT_ERROR,
+    }
+}
+
+fn barcode_format_from_code(code: i32) -> Option<BarcodeFormat> {
+    match code {
+        FORMAT_QR_CODE => Some(BarcodeFormat::QR_CODE),
+        FORMAT_AZTEC => Some(BarcodeFormat::AZTEC),
+        FORMAT_DATA_MATRIX => Some(BarcodeFormat::DATA_MATRIX),
+        FORMAT_PDF_417 => Some(BarcodeFormat::PDF_417),
+        FORMAT_CODE_128 => Some(BarcodeFormat::CODE_128),
+        FORMAT_CODE_39 => Some(BarcodeFormat::CODE_39),
+        FORMAT_EAN_13 => Some(BarcodeFormat::EAN_13),
+        FORMAT_EAN_8 => Some(BarcodeFormat::EAN_8),
+        FORMAT_UPC_A => Some(BarcodeFormat::UPC_A),
+        FORMAT_UPC_E => Some(BarcodeFormat::UPC_E),
+        _ => None,
+    }
+}
+
+fn format_code_from_barcode_format(format: &BarcodeFormat) -> i32 {
+    match format {
+        BarcodeFormat::QR_CODE => FORMAT_QR_CODE,
+        BarcodeFormat::AZTEC => FORMAT_AZTEC,
+        BarcodeFormat::DATA_MATRIX => FORMAT_DATA_MATRIX,
+        BarcodeFormat::PDF_417 => FORMAT_PDF_417,
+        BarcodeFormat::CODE_128 => FORMAT_CODE_128,
+        BarcodeFormat::CODE_39 => FORMAT_CODE_39,
+        BarcodeFormat::EAN_13 => FORMAT_EAN_13,
+        BarcodeFormat::EAN_8 => FORMAT_EAN_8,
+        BarcodeFormat::UPC_A => FORMAT_UPC_A,
+        BarcodeFormat::UPC_E => FORMAT_UPC_E,
+        _ => FORMAT_UNKNOWN,
+    }
+}
+
+/// Narrows the candidate formats using the source image's aspect ratio, following
+/// KItinerary's heuristic: a close-to-square image is most likely a 2D matrix code
+/// (QR/Aztec/Data Matrix), a moderately elongated one is likely PDF417, and a very
+/// elongated one is likely a 1D barcode. The middle of the range is ambiguous
+/// between PDF417 and 1D, so both are offered there.
+fn candidate_formats_for_aspect_ratio(width: u32, height: u32) -> HashSet<BarcodeFormat> {
+    let long_side = width.max(height) as f64;
+    let short_side = width.min(height).max(1) as f64;
+    let aspect_ratio = long_side / short_side;
+
+    if aspect_ratio <= 1.25 {
+        HashSet::from([
+            BarcodeFormat::QR_CODE,
+            BarcodeFormat::AZTEC,
+            BarcodeFormat::DATA_MATRIX,
+        ])
+    } else if aspect_ratio <= 1.95 {
+        HashSet::from([BarcodeFormat::PDF_417])
+    } else if aspect_ratio <= 6.5 {
+        HashSet::from([
+            BarcodeFormat::PDF_417,
+            BarcodeFormat::CODE_128,
+            BarcodeFormat::CODE_39,
+            BarcodeFormat::EAN_13,
+            BarcodeFormat::EAN_8,
+            BarcodeFormat::UPC_A,
+            BarcodeFormat::UPC_E,
+        ])
+    } else {
+        // Very elongated images past the PDF417 range are, if anything, even more
+        // likely to be a 1D barcode (e.g. a ~900x100px code with wide quiet zones),
+        // so keep offering the same 1D format set rather than falling back to QR.
+        HashSet::from([
+            BarcodeFormat::CODE_128,
+            BarcodeFormat::CODE_39,
+            BarcodeFormat::EAN_13,
+            BarcodeFormat::EAN_8,
+            BarcodeFormat::UPC_A,
+            BarcodeFormat::UPC_E,
+        ])
+    }
+}
+
+fn is_within_sane_pixel_bounds(width: u32, height: u32) -> bool {
+    let (long_side, short_side) = (width.max(height), width.min(height));
+    long_side >= MIN_IMAGE_WIDTH
+        && short_side >= MIN_IMAGE_HEIGHT
+        && long_side <= MAX_IMAGE_WIDTH
+        && short_side <= MAX_IMAGE_HEIGHT
+}
+
+#[no_mangle]
+pub extern "C" fn decode_multiple_qr_codes_from_image(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    decoded: *mut u16,
+    decoded_length: usize,
+    code_lengths: *mut usize,
+    code_lengths_count: usize,
+) -> i32 {
+    let image_buffer = unsafe { std::slice::from_raw_parts(image_buffer, image_buffer_len) };
+
+    let image = match image::load_from_memory(image_buffer) {
+        Ok(image) => image,
+        Err(_) => return IMAGE_ERROR,
+    };
+
+    process_multiple_results(
+        decode_multiple_in_image(image),
+        decoded,
+        decoded_length,
+        code_lengths,
+        code_lengths_count,
+    )
+}
+
+fn decode_multiple_in_image(img: DynamicImage) -> Result<Vec<RXingResult>, Exceptions> {
+    if !is_within_sane_pixel_bounds(img.width(), img.height()) {
+        return Err(Exceptions::NotFoundException(None));
+    }
+
+    let hints = DecodeHints::default()
+        .with(DecodeHintValue::TryHarder(true))
+        .with(DecodeHintValue::PossibleFormats(HashSet::from([
+            BarcodeFormat::QR_CODE,
+        ])));
+
+    let mut reader = GenericMultipleBarcodeReader::new(QRCodeMultiReader::default());
+    reader.decode_multiple_with_hints(
+        &mut BinaryBitmap::new(HybridBinarizer::new(BufferedImageLuminanceSource::new(img))),
+        &hints,
+    )
+}
+
+/// Writes the UTF-16 text of each decoded result back-to-back into `decoded`,
+/// recording the UTF-16 code unit length of the `i`th result in `code_lengths[i]`
+/// so the caller can split the concatenated buffer back into individual strings.
+fn process_multiple_results(
+    r: Result<Vec<RXingResult>, Exceptions>,
+    decoded: *mut u16,
+    decoded_length: usize,
+    code_lengths: *mut usize,
+    code_lengths_count: usize,
+) -> i32 {
+    match r {
+        Ok(results) => {
+            let decoded_slice = unsafe { std::slice::from_raw_parts_mut(decoded, decoded_length) };
+            let code_lengths_slice =
+                unsafe { std::slice::from_raw_parts_mut(code_lengths, code_lengths_count) };
+
+            let mut offset = 0;
+            for (i, result) in results.iter().enumerate() {
+                let mut code_len = 0;
+                for c in result.getText().encode_utf16() {
+                    if offset < decoded_length {
+                        decoded_slice[offset] = c;
+                    }
+                    offset += 1;
+                    code_len += 1;
+                }
+                if i < code_lengths_count {
+                    code_lengths_slice[i] = code_len;
+                }
+            }
+
+            results.len() as i32
+        }
+        Err(e) => match e {
+            rxing::Exceptions::NotFoundException(_) => QR_DECODE_NO_QR_CODE,
+            _ => QR_DECODE_ERROR,
+        },
+    }
+}
+
+#[no_mangle]
+pub extern "C" fn decode_qr_code_details_from_image(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    raw_bytes: *mut u8,
+    raw_bytes_capacity: usize,
+    points: *mut f32,
+    points_capacity: usize,
+    details: *mut QrCodeDetails,
+) -> i32 {
+    decode_qr_code_details_from_image_impl(
+        image_buffer,
+        image_buffer_len,
+        false,
+        raw_bytes,
+        raw_bytes_capacity,
+        points,
+        points_capacity,
+        details,
+    )
+}
+
+/// Same as [`decode_qr_code_details_from_image`], with the slower
+/// binarizer/rotation fallback chain available via `exhaustive`. See
+/// [`decode_qr_code_from_image_exhaustive`] for why this is a separate export.
+#[no_mangle]
+pub extern "C" fn decode_qr_code_details_from_image_exhaustive(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    exhaustive: bool,
+    raw_bytes: *mut u8,
+    raw_bytes_capacity: usize,
+    points: *mut f32,
+    points_capacity: usize,
+    details: *mut QrCodeDetails,
+) -> i32 {
+    decode_qr_code_details_from_image_impl(
+        image_buffer,
+        image_buffer_len,
+        exhaustive,
+        raw_bytes,
+        raw_bytes_capacity,
+        points,
+        points_capacity,
+        details,
+    )
+}
+
+fn decode_qr_code_details_from_image_impl(
+    image_buffer: *const u8,
+    image_buffer_len: usize,
+    exhaustive: bool,
+    raw_bytes: *mut u8,
+    raw_bytes_capacity: usize,
+    points: *mut f32,
+    points_capacity: usize,
+    details: *mut QrCodeDetails,
+) -> i32 {
+    let image_buffer = unsafe { std::slice::from_raw_parts(image_buffer, image_buffer_len) };
+
+    let image = match image::load_from_memory(image_buffer) {
+        Ok(image) => image,
+        Err(_) => return IMAGE_ERROR,
+    };
+
+    process_details_result(
+        detect_in_file_with_hints(
+            image,
+            Some(HashSet::from([BarcodeFormat::QR_CODE])),
+            exhaustive,
+        ),
+        raw_bytes,
+        raw_bytes_capacity,
+        points,
+        points_capacity,
+        details,
+    )
+}
+
+fn process_details_result(
+    r: Result<RXingResult, Exceptions>,
+    raw_bytes: *mut u8,
+    raw_bytes_capacity: usize,
+    points: *mut f32,
+    points_capacity: usize,
+    details: *mut QrCodeDetails,
+) -> i32 {
+    match r {
+        Ok(r) => {
+            let raw_bytes_slice =
+                unsafe { std::slice::from_raw_parts_mut(raw_bytes, raw_bytes_capacity) };
+            let source_bytes = r.getRawBytes();
+            for (i, b) in source_bytes.iter().enumerate() {
+                if i < raw_bytes_capacity {
+                    raw_bytes_slice[i] = *b;
+                }
+            }
+
+            let points_slice = unsafe { std::slice::from_raw_parts_mut(points, points_capacity) };
+            let mut point_count = 0;
+            for point in r.getRXingResultPoints() {
+                if point_count * 2 + 1 < points_capacity {
+                    points_slice[point_count * 2] = point.getX();
+                    points_slice[point_count * 2 + 1] = point.getY();
+                }
+                point_count += 1;
+            }
+
+            let metadata = r.getRXingResultMetadata();
+            let ecc_level = match metadata.get(&RXingResultMetadataType::ERROR_CORRECTION_LEVEL) {
+                Some(RXingResultMetadataValue::ErrorCorrectionLevel(level)) => {
+                    level.as_bytes().first().copied().unwrap_or(0)
+                }
+                _ => 0,
+            };
+            let symbol_version = match metadata.get(&RXingResultMetadataType::SYMBOL_VERSION) {
+                Some(RXingResultMetadataValue::SymbolVersion(version)) => *version,
+                _ => -1,
+            };
+
+            unsafe {
+                (*details).format = format_code_from_barcode_format(r.getBarcodeFormat());
+                (*details).ecc_level = ecc_level;
+                (*details).symbol_version = symbol_version;
+                (*details).raw_bytes_length = source_bytes.len();
+                (*details).point_count = point_count;
+            }
+
+            // Not `source_bytes.len()`: a validly-decoded symbol can carry an empty
+            // raw-byte payload, and that would collide with the `QR_DECODE_NO_QR_CODE`
+            // sentinel below. The real count is already in `details.raw_bytes_length`.
+            1
+        }
+        Err(e) => match e {
+            rxing::Exceptions::NotFoundException(_) => QR_DECODE_NO_QR_CODE,
+            _ => QR_DECODE_ERROR,
+        },
+    }
+}
+
 fn process_result(
     r: Result<RXingResult, Exceptions>,
     decoded: *mut u16,
@@ -80,17 +800,82 @@ fn process_result(
 
 fn detect_in_file_with_hints(
     img: DynamicImage,
-    barcode_type: Option<BarcodeFormat>,
+    formats: Option<HashSet<BarcodeFormat>>,
+    exhaustive: bool,
 ) -> Result<RXingResult, Exceptions> {
+    let (width, height) = (img.width(), img.height());
+    if !is_within_sane_pixel_bounds(width, height) {
+        return Err(Exceptions::NotFoundException(None));
+    }
+
     let mut multi_format_reader = MultiFormatReader::default();
 
     let mut hints = DecodeHints::default().with(DecodeHintValue::TryHarder(true));
-    if let Some(bc_type) = barcode_type {
-        hints = hints.with(DecodeHintValue::PossibleFormats(HashSet::from([bc_type])));
+    let formats = formats.unwrap_or_else(|| candidate_formats_for_aspect_ratio(width, height));
+    if !formats.is_empty() {
+        hints = hints.with(DecodeHintValue::PossibleFormats(formats));
     }
 
-    multi_format_reader.decode_with_hints(
-        &mut BinaryBitmap::new(HybridBinarizer::new(BufferedImageLuminanceSource::new(img))),
-        &hints,
-    )
+    let result = decode_with_binarizer(&mut multi_format_reader, img.clone(), &hints, false);
+    if !exhaustive {
+        return result;
+    }
+
+    match result {
+        Err(Exceptions::NotFoundException(_)) => {
+            decode_exhaustively(&mut multi_format_reader, img, &hints)
+        }
+        other => other,
+    }
+}
+
+/// Retries a decode that failed under the default `HybridBinarizer` with
+/// `GlobalHistogramBinarizer`, and with the image rotated 90°/180°, since a
+/// binarizer or orientation that fails on one low-contrast or unevenly lit
+/// capture can succeed on another. Only invoked when the caller opts into the
+/// slower "exhaustive" mode.
+fn decode_exhaustively(
+    reader: &mut MultiFormatReader,
+    img: DynamicImage,
+    hints: &DecodeHints,
+) -> Result<RXingResult, Exceptions> {
+    // The unrotated image already failed with the HybridBinarizer above, so only
+    // its GlobalHistogramBinarizer retry is left; each rotation gets both.
+    match decode_with_binarizer(reader, img.clone(), hints, true) {
+        Ok(r) => return Ok(r),
+        Err(Exceptions::NotFoundException(_)) => {}
+        Err(e) => return Err(e),
+    }
+
+    for rotated in [img.rotate90(), img.rotate180()] {
+        for use_global_histogram in [false, true] {
+            match decode_with_binarizer(reader, rotated.clone(), hints, use_global_histogram) {
+                Ok(r) => return Ok(r),
+                Err(Exceptions::NotFoundException(_)) => {}
+                Err(e) => return Err(e),
+            }
+        }
+    }
+
+    Err(Exceptions::NotFoundException(None))
+}
+
+fn decode_with_binarizer(
+    reader: &mut MultiFormatReader,
+    img: DynamicImage,
+    hints: &DecodeHints,
+    use_global_histogram: bool,
+) -> Result<RXingResult, Exceptions> {
+    let luminance_source = BufferedImageLuminanceSource::new(img);
+    if use_global_histogram {
+        reader.decode_with_hints(
+            &mut BinaryBitmap::new(GlobalHistogramBinarizer::new(luminance_source)),
+            hints,
+        )
+    } else {
+        reader.decode_with_hints(
+            &mut BinaryBitmap::new(HybridBinarizer::new(luminance_source)),
+            hints,
+        )
+    }
 }